@@ -0,0 +1,220 @@
+//! The merkleization cache backing `Partial`: a sparse map from
+//! generalized Merkle tree index to its 32-byte chunk, refreshed
+//! bottom-up after `set_bytes` calls.
+//!
+//! The module doc used to admit this "doesn't yet use SSZ merkleization";
+//! this is that piece, including a cached zero-hash table so a caller can
+//! submit a partial that omits all-zero subtrees and still get the correct
+//! root back out of `refresh`.
+//!
+//! Once a root has already been computed once, `refresh` only walks the
+//! paths-to-root of leaves touched by `set` since the last refresh
+//! (tracked in `dirty`), rather than recomputing the whole tree.
+
+use crate::error::Error;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn hash_children(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// `zero_hashes()[d]` is the root of an all-zero subtree of depth `d`
+/// (`zero_hashes()[0]` is a single zero chunk, not yet hashed with
+/// anything). Computed once, lazily, up to depth 40 — comfortably past any
+/// tree this crate will be asked to merkleize.
+fn zero_hashes() -> &'static Vec<[u8; 32]> {
+    static TABLE: Lazy<Vec<[u8; 32]>> = Lazy::new(|| {
+        let mut table = vec![[0u8; 32]];
+        for _ in 0..40 {
+            let last = *table.last().expect("seeded with one entry");
+            let mut next = [0u8; 32];
+            next.copy_from_slice(&hash_children(&last, &last));
+            table.push(next);
+        }
+        table
+    });
+    &TABLE
+}
+
+pub fn zero_hash(depth: u8) -> &'static [u8] {
+    &zero_hashes()[depth as usize]
+}
+
+/// `index`'s depth below the root, i.e. `floor(log2(index))`. The root
+/// itself (index 1) is depth 0.
+fn depth_of(index: u64) -> u8 {
+    63 - index.leading_zeros() as u8
+}
+
+#[derive(Debug, Default)]
+pub struct Cache {
+    chunks: BTreeMap<u64, Vec<u8>>,
+    /// Leaves set since the last `refresh`. Empty means either nothing
+    /// has been mutated yet, or the last `refresh` already caught up.
+    dirty: BTreeSet<u64>,
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Cache {
+            chunks: BTreeMap::new(),
+            dirty: BTreeSet::new(),
+        }
+    }
+
+    /// Loads a `SerializedPartial`'s flat `indices`/`chunks` into the
+    /// working cache. These are the proof's starting data, not a mutation,
+    /// so they don't mark anything dirty — unless index 1 itself was
+    /// supplied, the first `refresh` computes every ancestor from scratch.
+    pub fn fill(&mut self, indices: &[u64], chunks: &[u8]) -> Result<(), Error> {
+        if chunks.len() != indices.len() * 32 {
+            return Err(Error::InvalidSerializedPartial);
+        }
+
+        for (i, index) in indices.iter().enumerate() {
+            let start = i * 32;
+            self.chunks
+                .insert(*index, chunks[start..start + 32].to_vec());
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, index: u64) -> Option<&[u8]> {
+        self.chunks.get(&index).map(Vec::as_slice)
+    }
+
+    pub fn set(&mut self, index: u64, chunk: Vec<u8>) {
+        self.chunks.insert(index, chunk);
+        self.dirty.insert(index);
+    }
+
+    pub fn indices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Recomputes whatever's needed to bring the root up to date. The
+    /// first call (nothing cached at index 1 yet) walks every ancestor of
+    /// the cache's deepest nodes, pulling in a cached zero-subtree hash
+    /// for any sibling that wasn't explicitly supplied. Every call after
+    /// that only re-hashes the paths-to-root of leaves touched by `set`
+    /// since the last `refresh` — a node is re-hashed exactly when at
+    /// least one of its children changed — which is `O(dirty leaves *
+    /// tree height)` instead of the whole tree.
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        if self.chunks.is_empty() {
+            return Err(Error::UnableToRefresh);
+        }
+
+        if self.chunks.contains_key(&1) {
+            return self.refresh_dirty();
+        }
+
+        let height = self
+            .chunks
+            .keys()
+            .map(|&i| depth_of(i))
+            .max()
+            .unwrap_or(0);
+
+        // Climb one whole depth level at a time, deepest first. A single
+        // pass over a mixed-depth frontier (the old behavior) could
+        // promote a shallow supplied node — e.g. the length leaf at depth
+        // 1 — straight to the root before the deeper subtrees under it
+        // were built, at which point the `while` condition saw index 1
+        // present and stopped, leaving the root silently wrong instead of
+        // erroring. Index 1 is depth 0, so it's the very last thing
+        // computed here, once every other depth has had a chance to feed
+        // into it.
+        for depth in (1..=height).rev() {
+            let frontier: Vec<u64> = self
+                .chunks
+                .keys()
+                .copied()
+                .filter(|&i| depth_of(i) == depth)
+                .collect();
+
+            for index in frontier {
+                let parent = index / 2;
+                if self.chunks.contains_key(&parent) {
+                    continue;
+                }
+
+                let sibling = index ^ 1;
+                let sibling_chunk = match self.chunks.get(&sibling) {
+                    Some(chunk) => chunk.clone(),
+                    None => zero_hash(height - depth).to_vec(),
+                };
+
+                let this_chunk = self.chunks[&index].clone();
+                let (left, right) = if index % 2 == 0 {
+                    (this_chunk, sibling_chunk)
+                } else {
+                    (sibling_chunk, this_chunk)
+                };
+
+                self.chunks.insert(parent, hash_children(&left, &right));
+            }
+        }
+
+        if !self.chunks.contains_key(&1) {
+            return Err(Error::UnableToRefresh);
+        }
+
+        self.dirty.clear();
+        Ok(())
+    }
+
+    fn refresh_dirty(&mut self) -> Result<(), Error> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        let height = self
+            .chunks
+            .keys()
+            .map(|&i| depth_of(i))
+            .max()
+            .unwrap_or(0);
+
+        let mut frontier: BTreeSet<u64> = self.dirty.iter().copied().filter(|&i| i > 1).collect();
+
+        while !frontier.is_empty() {
+            let mut parents = BTreeSet::new();
+            for index in frontier {
+                let parent = index / 2;
+                let depth = depth_of(index);
+                let sibling = index ^ 1;
+                let sibling_chunk = match self.chunks.get(&sibling) {
+                    Some(chunk) => chunk.clone(),
+                    None => zero_hash(height - depth).to_vec(),
+                };
+
+                let this_chunk = self.chunks[&index].clone();
+                let (left, right) = if index % 2 == 0 {
+                    (this_chunk, sibling_chunk)
+                } else {
+                    (sibling_chunk, this_chunk)
+                };
+
+                self.chunks.insert(parent, hash_children(&left, &right));
+                if parent > 1 {
+                    parents.insert(parent);
+                }
+            }
+            frontier = parents;
+        }
+
+        self.dirty.clear();
+        Ok(())
+    }
+
+    pub fn root(&self) -> Result<&[u8], Error> {
+        self.chunks.get(&1).map(Vec::as_slice).ok_or(Error::MissingRoot)
+    }
+}
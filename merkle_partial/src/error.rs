@@ -0,0 +1,36 @@
+//! Errors surfaced by `Partial` and `SerializedPartial` operations.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A `Path` segment doesn't resolve to anything in this type's layout
+    /// (e.g. indexing past a fixed-length vector's bound, or an unknown
+    /// field name).
+    InvalidPath(String),
+    /// `SerializedPartial.indices` and `.chunks` didn't agree in length, or
+    /// a chunk wasn't a full 32 bytes.
+    InvalidSerializedPartial,
+    /// `refresh`/`verify` ran out of nodes to combine before reaching the
+    /// root, meaning the supplied partial didn't carry enough of the tree
+    /// to reconstruct it.
+    UnableToRefresh,
+    /// A node that was asked for isn't in the cache: the root for `root()`
+    /// before it has been computed, a leaf for `get_bytes`, or a target or
+    /// helper chunk for `prove`.
+    MissingRoot,
+    /// A proof failed to recompute the expected root.
+    RootMismatch,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::InvalidPath(p) => write!(f, "invalid path: {}", p),
+            Error::InvalidSerializedPartial => write!(f, "malformed serialized partial"),
+            Error::UnableToRefresh => write!(f, "partial did not carry enough of the tree to refresh"),
+            Error::MissingRoot => write!(f, "root has not been computed yet"),
+            Error::RootMismatch => write!(f, "recomputed root did not match the expected root"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
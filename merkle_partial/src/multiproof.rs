@@ -0,0 +1,42 @@
+//! Minimal-multiproof index selection: the inverse of filling a
+//! `SerializedPartial` into a tree. Given a set of target generalized
+//! indices, works out the smallest additional set of sibling ("helper")
+//! indices a verifier needs to recompute the root without the rest of the
+//! tree.
+
+use std::collections::BTreeSet;
+
+/// `index` and every one of its ancestors up to and including the root.
+pub fn path_indices(index: u64) -> Vec<u64> {
+    let mut path = vec![index];
+    let mut current = index;
+    while current > 1 {
+        current /= 2;
+        path.push(current);
+    }
+    path
+}
+
+/// The sibling of `index` at every level from `index` up to (but not
+/// including) the root — exactly the nodes a verifier is missing to
+/// recompute `index`'s path to the root.
+pub fn branch_indices(index: u64) -> Vec<u64> {
+    let mut branch = Vec::new();
+    let mut current = index;
+    while current > 1 {
+        branch.push(current ^ 1);
+        current /= 2;
+    }
+    branch
+}
+
+/// The minimal helper set for a batch of `targets`: every sibling needed
+/// by any target's path, minus anything that's already on some target's
+/// own path (since a node on the path will be computed directly, not
+/// supplied as a helper).
+pub fn helper_indices(targets: &[u64]) -> BTreeSet<u64> {
+    let paths: BTreeSet<u64> = targets.iter().flat_map(|&t| path_indices(t)).collect();
+    let branches: BTreeSet<u64> = targets.iter().flat_map(|&t| branch_indices(t)).collect();
+
+    branches.difference(&paths).copied().collect()
+}
@@ -0,0 +1,9 @@
+//! A single step of a field-access path into a `#[derive(Partial)]` type,
+//! e.g. `messages[3].timestamp` is
+//! `[Path::Ident("messages"), Path::Index(3), Path::Ident("timestamp")]`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Path {
+    Ident(String),
+    Index(u64),
+}
@@ -0,0 +1,65 @@
+//! The wire format for a partial Merkle proof: a flat list of generalized
+//! indices and their corresponding 32-byte chunks, in matching order.
+
+use crate::cache::hash_children;
+use crate::error::Error;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, PartialEq, ssz_derive::Encode, ssz_derive::Decode)]
+pub struct SerializedPartial {
+    pub indices: Vec<u64>,
+    pub chunks: Vec<u8>,
+}
+
+impl SerializedPartial {
+    /// Recomputes the root bottom-up from exactly the provided
+    /// indices/chunks and compares it against `expected_root`, without
+    /// materializing a `Cache` or falling back to the zero-hash table for
+    /// missing siblings. Intended for untrusted input (e.g. a shard
+    /// block's execution data) where a caller must not be able to pass a
+    /// partial that looks valid but omits part of the tree: if a sibling
+    /// is genuinely an all-zero subtree, it still has to be supplied
+    /// explicitly as a chunk.
+    pub fn verify(&self, expected_root: &[u8]) -> Result<bool, Error> {
+        if self.chunks.len() != self.indices.len() * 32 {
+            return Err(Error::InvalidSerializedPartial);
+        }
+
+        let mut objects: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        for (i, index) in self.indices.iter().enumerate() {
+            let start = i * 32;
+            objects.insert(*index, self.chunks[start..start + 32].to_vec());
+        }
+
+        let mut worklist: Vec<u64> = objects.keys().copied().filter(|&i| i > 1).collect();
+        worklist.sort_unstable_by(|a, b| b.cmp(a));
+
+        while let Some(index) = worklist.pop() {
+            let parent = index / 2;
+            if objects.contains_key(&parent) {
+                continue;
+            }
+
+            let sibling = index ^ 1;
+            let (this_chunk, sibling_chunk) = match (objects.get(&index), objects.get(&sibling)) {
+                (Some(a), Some(b)) => (a.clone(), b.clone()),
+                _ => continue,
+            };
+
+            let (left, right) = if index % 2 == 0 {
+                (this_chunk, sibling_chunk)
+            } else {
+                (sibling_chunk, this_chunk)
+            };
+
+            objects.insert(parent, hash_children(&left, &right));
+            if parent > 1 {
+                worklist.push(parent);
+                worklist.sort_unstable_by(|a, b| b.cmp(a));
+            }
+        }
+
+        let root = objects.get(&1).ok_or(Error::UnableToRefresh)?;
+        Ok(root.as_slice() == expected_root)
+    }
+}
@@ -0,0 +1,314 @@
+//! `#[derive(Partial)]`: generates a `merkle_partial::Layout` impl that
+//! maps a struct's fields onto generalized tree indices, mirroring how
+//! `ssz_derive` generates `Encode`/`Decode` for the same types.
+//!
+//! Fields are assigned to leaf slots in declaration order. A run of
+//! consecutive "basic" fields (`u8`/`u16`/`u32`/`u64`/`u128`/`bool`, or a
+//! `FixedVector<u8, N>` with `N <= 32`) is packed byte-range-wise into a
+//! shared 32-byte leaf exactly like SSZ packs basic types into chunks; a
+//! field of any other type (a nested `#[derive(Partial)]` struct, or a
+//! `VariableList<_, _>`) always starts a fresh leaf slot, since it has its
+//! own subtree. `VariableList<Elem, N>` fields additionally support a
+//! trailing `Path::Ident("len")` segment resolving to the list's
+//! length-mixin leaf.
+//!
+//! Nested subtrees are addressed via `merkle_partial::subtree_index_to_general`,
+//! called once per level of nesting the generated `resolve` descends, so
+//! composition stays correct no matter how deeply `#[derive(Partial)]`
+//! containers and `VariableList`s of them nest. Each list element gets a
+//! subtree of its own, rooted at leaf `i` of the list's data subtree.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericArgument, PathArguments, Type};
+
+enum FieldLayout {
+    /// A run of packed primitive fields sharing one leaf.
+    Packed(Vec<PackedField>),
+    /// A field with a subtree of its own: a nested `Layout` type, or a
+    /// `VariableList<Elem, N>`.
+    Composite { name: syn::Ident, kind: CompositeKind },
+}
+
+struct PackedField {
+    name: syn::Ident,
+    offset: usize,
+    size: usize,
+    /// A `FixedVector<u8, N>` supports a further `Path::Index(j)` to
+    /// address a single byte at `offset + j`; a scalar field does not.
+    is_byte_array: bool,
+}
+
+enum CompositeKind {
+    Nested(Type),
+    List { elem: Type, capacity: u64 },
+}
+
+fn basic_size(ty: &Type) -> Option<usize> {
+    let ident = match ty {
+        Type::Path(p) => p.path.segments.last()?.ident.to_string(),
+        _ => return None,
+    };
+    match ident.as_str() {
+        "bool" | "u8" | "i8" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" => Some(4),
+        "u64" | "i64" => Some(8),
+        "u128" | "i128" => Some(16),
+        _ => None,
+    }
+}
+
+/// `FixedVector<u8, UN>` with `N <= 32` can be packed as a single leaf,
+/// addressed byte-by-byte.
+fn fixed_byte_vector_size(ty: &Type) -> Option<usize> {
+    let Type::Path(p) = ty else { return None };
+    let segment = p.path.segments.last()?;
+    if segment.ident != "FixedVector" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let mut args = args.args.iter();
+    let elem = args.next()?;
+    let len = args.next()?;
+
+    let GenericArgument::Type(Type::Path(elem_path)) = elem else {
+        return None;
+    };
+    if elem_path.path.segments.last()?.ident != "u8" {
+        return None;
+    }
+
+    let GenericArgument::Type(Type::Path(len_path)) = len else {
+        return None;
+    };
+    let len_ident = len_path.path.segments.last()?.ident.to_string();
+    let n: usize = len_ident.strip_prefix('U')?.parse().ok()?;
+    if n <= 32 {
+        Some(n)
+    } else {
+        None
+    }
+}
+
+fn variable_list(ty: &Type) -> Option<(Type, u64)> {
+    let Type::Path(p) = ty else { return None };
+    let segment = p.path.segments.last()?;
+    if segment.ident != "VariableList" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let mut args = args.args.iter();
+    let elem = args.next()?;
+    let capacity = args.next()?;
+
+    let GenericArgument::Type(elem_ty) = elem else {
+        return None;
+    };
+    let GenericArgument::Type(Type::Path(capacity_path)) = capacity else {
+        return None;
+    };
+    let capacity_ident = capacity_path.path.segments.last()?.ident.to_string();
+    let n: u64 = capacity_ident.strip_prefix('U')?.parse().ok()?;
+
+    Some((elem_ty.clone(), n))
+}
+
+fn layout_fields(fields: &Fields) -> Vec<FieldLayout> {
+    let Fields::Named(named) = fields else {
+        panic!("#[derive(Partial)] only supports structs with named fields");
+    };
+
+    let mut layout = Vec::new();
+    let mut pending: Vec<PackedField> = Vec::new();
+    let mut offset = 0usize;
+
+    let flush = |pending: &mut Vec<PackedField>, layout: &mut Vec<FieldLayout>| {
+        if !pending.is_empty() {
+            layout.push(FieldLayout::Packed(std::mem::take(pending)));
+        }
+    };
+
+    for field in &named.named {
+        let name = field.ident.clone().expect("named field");
+
+        if let Some((elem, capacity)) = variable_list(&field.ty) {
+            flush(&mut pending, &mut layout);
+            offset = 0;
+            layout.push(FieldLayout::Composite {
+                name,
+                kind: CompositeKind::List { elem, capacity },
+            });
+            continue;
+        }
+
+        let byte_array_size = fixed_byte_vector_size(&field.ty);
+        let size = basic_size(&field.ty).or(byte_array_size);
+        if let Some(size) = size {
+            if offset + size > 32 {
+                flush(&mut pending, &mut layout);
+                offset = 0;
+            }
+            pending.push(PackedField {
+                name,
+                offset,
+                size,
+                is_byte_array: byte_array_size.is_some(),
+            });
+            offset += size;
+            continue;
+        }
+
+        flush(&mut pending, &mut layout);
+        offset = 0;
+        layout.push(FieldLayout::Composite {
+            name,
+            kind: CompositeKind::Nested(field.ty.clone()),
+        });
+    }
+    flush(&mut pending, &mut layout);
+
+    layout
+}
+
+/// Generates the body run once a field's own name has already matched
+/// `path[0]`; `rest` (`= &path[1..]`, bound by the caller) is whatever
+/// comes after the field name. `local_index` is this field's slot,
+/// already folded into generalized-index form (`2^d + slot`) at macro
+/// expansion time, since the slot layout is fixed by the struct
+/// definition.
+fn packed_field_body(local_index: u64, offset: usize, size: usize, is_byte_array: bool) -> TokenStream2 {
+    if is_byte_array {
+        quote! {
+            match rest.first() {
+                Some(merkle_partial::Path::Index(j)) if (*j as usize) < #size => Ok(merkle_partial::Primitive {
+                    index: merkle_partial::subtree_index_to_general(root_gindex, #local_index),
+                    offset: #offset + *j as usize,
+                    size: 1,
+                }),
+                None => Ok(merkle_partial::Primitive {
+                    index: merkle_partial::subtree_index_to_general(root_gindex, #local_index),
+                    offset: #offset,
+                    size: #size,
+                }),
+                _ => Err(merkle_partial::Error::InvalidPath(format!("{:?}", rest))),
+            }
+        }
+    } else {
+        quote! {
+            Ok(merkle_partial::Primitive {
+                index: merkle_partial::subtree_index_to_general(root_gindex, #local_index),
+                offset: #offset,
+                size: #size,
+            })
+        }
+    }
+}
+
+fn composite_field_body(local_index: u64, kind: &CompositeKind) -> TokenStream2 {
+    match kind {
+        CompositeKind::Nested(elem) => quote! {
+            <#elem as merkle_partial::Layout>::resolve(rest).map(|leaf| merkle_partial::Primitive {
+                index: merkle_partial::subtree_index_to_general(merkle_partial::subtree_index_to_general(root_gindex, #local_index), leaf.index),
+                offset: leaf.offset,
+                size: leaf.size,
+            })
+        },
+        CompositeKind::List { elem, capacity } => quote! {
+            {
+                // A `VariableList<Elem, N>` field's own subtree is a
+                // 2-child (data, length) node (the `mix_in_length` from
+                // real SSZ merkleization), itself sitting at this
+                // field's slot.
+                let field_root = merkle_partial::subtree_index_to_general(root_gindex, #local_index);
+                let data_root = merkle_partial::subtree_index_to_general(field_root, 2);
+                match rest.first() {
+                    Some(merkle_partial::Path::Ident(ident)) if ident.as_str() == "len" => {
+                        Ok(merkle_partial::Primitive {
+                            index: merkle_partial::subtree_index_to_general(field_root, 3),
+                            offset: 0,
+                            size: 32,
+                        })
+                    }
+                    Some(merkle_partial::Path::Index(i)) => {
+                        let item_root = merkle_partial::subtree_index_to_general(
+                            data_root,
+                            #capacity.next_power_of_two().max(1) + i,
+                        );
+                        let item_leaf = <#elem as merkle_partial::Layout>::resolve(&rest[1..])?;
+                        Ok(merkle_partial::Primitive {
+                            index: merkle_partial::subtree_index_to_general(item_root, item_leaf.index),
+                            offset: item_leaf.offset,
+                            size: item_leaf.size,
+                        })
+                    }
+                    _ => Err(merkle_partial::Error::InvalidPath(format!("{:?}", rest))),
+                }
+            }
+        },
+    }
+}
+
+#[proc_macro_derive(Partial)]
+pub fn derive_partial(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        panic!("#[derive(Partial)] only supports structs");
+    };
+
+    let fields = layout_fields(&data.fields);
+    let slot_count = fields.len() as u64;
+    let slot_base = slot_count.next_power_of_two().max(1);
+
+    let mut name_arms = Vec::new();
+    for (slot, field) in fields.iter().enumerate() {
+        let local_index = slot_base + slot as u64;
+        match field {
+            FieldLayout::Packed(packed) => {
+                for f in packed {
+                    let field_name = f.name.to_string();
+                    let body = packed_field_body(local_index, f.offset, f.size, f.is_byte_array);
+                    name_arms.push(quote! { #field_name => #body });
+                }
+            }
+            FieldLayout::Composite { name, kind } => {
+                let field_name = name.to_string();
+                let body = composite_field_body(local_index, kind);
+                name_arms.push(quote! { #field_name => #body });
+            }
+        }
+    }
+
+    let expanded = quote! {
+        impl merkle_partial::Layout for #name {
+            const LEAF_COUNT: u64 = #slot_count;
+
+            fn resolve(path: &[merkle_partial::Path]) -> Result<merkle_partial::Primitive, merkle_partial::Error> {
+                // Each `#[derive(Partial)]` impl resolves assuming its
+                // own root sits at generalized index 1; a composite
+                // field's arm above splices the callee's result under
+                // its own slot via `merkle_partial::subtree_index_to_general`.
+                let root_gindex = merkle_partial::ROOT_INDEX;
+
+                match path.split_first() {
+                    Some((merkle_partial::Path::Ident(name), rest)) => match name.as_str() {
+                        #(#name_arms,)*
+                        _ => Err(merkle_partial::Error::InvalidPath(name.clone())),
+                    },
+                    _ => Err(merkle_partial::Error::InvalidPath(format!("{:?}", path))),
+                }
+            }
+        }
+    };
+
+    expanded.into()
+}
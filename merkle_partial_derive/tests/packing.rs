@@ -0,0 +1,53 @@
+//! Integration tests for `#[derive(Partial)]`'s field-packing layout.
+//!
+//! `merkle_partial_derive` is a `proc-macro = true` crate, so it can't use
+//! its own derive macro from a unit test inside `src/`; these live here as
+//! ordinary tests against a small derived type instead. The types derived
+//! elsewhere (`scripts/bazaar_partials`'s `Message` and `State`) never
+//! exercise the packed-leaf path -- `Message`'s two fields already sum to
+//! 40 bytes and land on separate leaves, and `State` holds only a list --
+//! so nothing else in the tree covers it.
+
+use merkle_partial::{Layout, Partial, Path, SerializedPartial};
+
+#[derive(Debug, Default, merkle_partial_derive::Partial)]
+struct TwoScalars {
+    pub a: u32,
+    pub b: u64,
+}
+
+#[test]
+fn packs_two_sub_32_byte_fields_into_one_leaf() {
+    // 4 + 8 = 12 <= 32, so both fields should share one leaf slot rather
+    // than each starting a fresh one -- the whole point of the packed-run
+    // path in `layout_fields`.
+    let a = TwoScalars::resolve(&[Path::Ident("a".to_string())]).unwrap();
+    let b = TwoScalars::resolve(&[Path::Ident("b".to_string())]).unwrap();
+
+    assert_eq!(a.index, b.index);
+    assert_eq!(a.offset, 0);
+    assert_eq!(a.size, 4);
+    assert_eq!(b.offset, 4);
+    assert_eq!(b.size, 8);
+}
+
+#[test]
+fn set_bytes_on_one_packed_field_does_not_clobber_its_sibling() {
+    let mut partial: Partial<TwoScalars> = Partial::new(SerializedPartial::default());
+
+    partial
+        .set_bytes(vec![Path::Ident("a".to_string())], 7u32.to_le_bytes().to_vec())
+        .unwrap();
+    partial
+        .set_bytes(vec![Path::Ident("b".to_string())], 9u64.to_le_bytes().to_vec())
+        .unwrap();
+
+    assert_eq!(
+        partial.get_bytes(vec![Path::Ident("a".to_string())]).unwrap(),
+        7u32.to_le_bytes().to_vec()
+    );
+    assert_eq!(
+        partial.get_bytes(vec![Path::Ident("b".to_string())]).unwrap(),
+        9u64.to_le_bytes().to_vec()
+    );
+}
@@ -0,0 +1,81 @@
+//! The Phase 0 `Deposit` object and its Merkle root, so deposits produced by
+//! `eth2_pushNewDeposit` actually flow somewhere instead of being dropped.
+//! https://github.com/ethereum/eth2.0-specs/blob/dev/specs/core/0_beacon-chain.md
+
+use crate::hash_tree_root::hash_tree_root_list_bytes;
+use crate::types::Bytes32;
+
+/// SSZ-encoded byte length of a `Deposit`: 48 (pubkey) + 32
+/// (withdrawal_credentials) + 8 (amount) + 96 (signature).
+pub const DEPOSIT_SSZ_SIZE: usize = 48 + 32 + 8 + 96;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Deposit {
+    pub pubkey: [u8; 48],
+    pub withdrawal_credentials: Bytes32,
+    pub amount: u64,
+    pub signature: [u8; 96],
+}
+
+impl Default for Deposit {
+    fn default() -> Self {
+        Deposit {
+            pubkey: [0u8; 48],
+            withdrawal_credentials: Bytes32::default(),
+            amount: 0,
+            signature: [0u8; 96],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepositDecodeError {
+    /// The supplied byte slice wasn't exactly `DEPOSIT_SSZ_SIZE` bytes long.
+    InvalidLength { found: usize },
+}
+
+impl Deposit {
+    /// SSZ-decodes a `Deposit` from its fixed-size field layout: all fields
+    /// are fixed-length, so the container is just their concatenation in
+    /// declaration order, with no offsets to resolve.
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Deposit, DepositDecodeError> {
+        if bytes.len() != DEPOSIT_SSZ_SIZE {
+            return Err(DepositDecodeError::InvalidLength { found: bytes.len() });
+        }
+
+        let mut pubkey = [0u8; 48];
+        pubkey.copy_from_slice(&bytes[0..48]);
+
+        let withdrawal_credentials = Bytes32::from_slice(&bytes[48..80]);
+
+        let mut amount_bytes = [0u8; 8];
+        amount_bytes.copy_from_slice(&bytes[80..88]);
+        let amount = u64::from_le_bytes(amount_bytes);
+
+        let mut signature = [0u8; 96];
+        signature.copy_from_slice(&bytes[88..184]);
+
+        Ok(Deposit {
+            pubkey,
+            withdrawal_credentials,
+            amount,
+            signature,
+        })
+    }
+
+    fn as_ssz_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(DEPOSIT_SSZ_SIZE);
+        bytes.extend_from_slice(&self.pubkey);
+        bytes.extend_from_slice(&self.withdrawal_credentials.bytes);
+        bytes.extend_from_slice(&self.amount.to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+}
+
+/// Merkleizes a list of deposits into the `deposit_root` stored on
+/// `ShardState`, mixing in the deposit count per the SSZ list rules.
+pub fn deposit_root(deposits: &[Deposit]) -> Bytes32 {
+    let ssz_bytes: Vec<u8> = deposits.iter().flat_map(|d| d.as_ssz_bytes()).collect();
+    hash_tree_root_list_bytes(&ssz_bytes, deposits.len())
+}
@@ -0,0 +1,273 @@
+//! The EEI (Execution Environment Interface) host-function set, shared by
+//! every `ExecutionBackend`. This module is the wasmi-facing half: it wires
+//! `backend::HostState` up to wasmi's `Externals`/`ModuleImportResolver`
+//! traps. The wasmtime backend drives the same `HostState` through its own
+//! `Linker`, so the semantics here are the spec for both engines.
+
+use crate::backend::{HostState, OutOfGas};
+use crate::deposit::{Deposit, DEPOSIT_SSZ_SIZE};
+use crate::eei_error::{checked_range, EeiError};
+use crate::kzg::{self, KzgCommitment, KzgProof};
+use crate::ShardBlockBody;
+use wasmi::memory_units::Pages;
+use wasmi::{
+    Error as InterpreterError, Externals, FuncInstance, FuncRef, MemoryInstance, MemoryRef,
+    ModuleImportResolver, RuntimeArgs, RuntimeValue, Signature, Trap, TrapKind, ValueType,
+};
+
+/// Bytes per wasm linear-memory page, used to bounds-check a pointer/length
+/// pair against the instance's actual memory size.
+const WASM_PAGE_BYTES: usize = 65536;
+
+fn to_trap(e: EeiError) -> Trap {
+    Trap::new(TrapKind::Host(Box::new(e)))
+}
+
+impl std::fmt::Display for OutOfGas {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "out of gas")
+    }
+}
+
+impl std::error::Error for OutOfGas {}
+impl wasmi::HostError for OutOfGas {}
+
+fn out_of_gas_trap() -> Trap {
+    Trap::new(TrapKind::Host(Box::new(OutOfGas)))
+}
+
+/// Fuel charged per host call. This is the only metering hook the wasmi
+/// interpreter we wrap gives us -- there's no per-instruction counter to tap
+/// into the way wasmtime's `Config::consume_fuel` provides. It catches a
+/// call-heavy or looping script that keeps touching the EEI, but a tight
+/// compute loop that never calls a host function will not exhaust
+/// `HostState::fuel_remaining` under this backend; see `backend`'s module
+/// doc. That gap is an explicit non-goal for `wasmi_backend`, not something
+/// this constant is meant to close.
+const HOST_CALL_FUEL_COST: u64 = 1;
+
+fn memory_len(memory: &MemoryRef) -> usize {
+    memory.current_size().0 * WASM_PAGE_BYTES
+}
+
+/// Reads `length` bytes at `ptr` out of `memory`, checked against overflow
+/// and the instance's actual memory size.
+fn read_memory(memory: &MemoryRef, ptr: u32, length: usize) -> Result<Vec<u8>, EeiError> {
+    checked_range(ptr as usize, length, memory_len(memory))?;
+    memory
+        .get(ptr, length)
+        .map_err(|e| EeiError::Memory(e.to_string()))
+}
+
+/// Writes `data` to `ptr` in `memory`, checked the same way as
+/// `read_memory`.
+fn write_memory(memory: &MemoryRef, ptr: u32, data: &[u8]) -> Result<(), EeiError> {
+    checked_range(ptr as usize, data.len(), memory_len(memory))?;
+    memory
+        .set(ptr, data)
+        .map_err(|e| EeiError::Memory(e.to_string()))
+}
+
+pub const LOADPRESTATEROOT_FUNC_INDEX: usize = 0;
+pub const BLOCKDATASIZE_FUNC_INDEX: usize = 1;
+pub const BLOCKDATACOPY_FUNC_INDEX: usize = 2;
+pub const SAVEPOSTSTATEROOT_FUNC_INDEX: usize = 3;
+pub const PUSHNEWDEPOSIT_FUNC_INDEX: usize = 4;
+pub const EXECCODE_FUNC_INDEX: usize = 5;
+pub const VERIFYBLOBKZG_FUNC_INDEX: usize = 6;
+
+pub struct Runtime<'a> {
+    pub memory: Option<MemoryRef>,
+    host: HostState<'a>,
+}
+
+impl<'a> Runtime<'a> {
+    pub fn new(host: HostState<'a>) -> Runtime<'a> {
+        Runtime {
+            memory: Some(MemoryInstance::alloc(Pages(1), Some(Pages(1))).unwrap()),
+            host,
+        }
+    }
+
+    pub fn into_host_state(self) -> HostState<'a> {
+        self.host
+    }
+}
+
+impl<'a> Externals for Runtime<'a> {
+    fn invoke_index(
+        &mut self,
+        index: usize,
+        args: RuntimeArgs,
+    ) -> Result<Option<RuntimeValue>, Trap> {
+        self.host
+            .consume_fuel(HOST_CALL_FUEL_COST)
+            .map_err(|OutOfGas| out_of_gas_trap())?;
+
+        match index {
+            LOADPRESTATEROOT_FUNC_INDEX => {
+                let ptr: u32 = args.nth(0);
+                println!("loadprestateroot to {}", ptr);
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                write_memory(memory, ptr, &self.host.pre_state.bytes).map_err(to_trap)?;
+
+                Ok(None)
+            }
+            SAVEPOSTSTATEROOT_FUNC_INDEX => {
+                let ptr: u32 = args.nth(0);
+                println!("savepoststateroot from {}", ptr);
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                let root = read_memory(memory, ptr, 32).map_err(to_trap)?;
+                self.host.post_state.bytes.copy_from_slice(&root);
+
+                Ok(None)
+            }
+            BLOCKDATASIZE_FUNC_INDEX => {
+                let ret: i32 = self.host.block_data.data.len() as i32;
+                println!("blockdatasize {}", ret);
+                Ok(Some(ret.into()))
+            }
+            BLOCKDATACOPY_FUNC_INDEX => {
+                let ptr: u32 = args.nth(0);
+                let offset: u32 = args.nth(1);
+                let length: u32 = args.nth(2);
+                println!(
+                    "blockdatacopy to {} from {} for {} bytes",
+                    ptr, offset, length
+                );
+
+                let block_data = &self.host.block_data.data;
+                let range =
+                    checked_range(offset as usize, length as usize, block_data.len())
+                        .map_err(to_trap)?;
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                write_memory(memory, ptr, &block_data[range]).map_err(to_trap)?;
+
+                Ok(None)
+            }
+            PUSHNEWDEPOSIT_FUNC_INDEX => {
+                let ptr: u32 = args.nth(0);
+                println!("pushnewdeposit from {}", ptr);
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                let raw = read_memory(memory, ptr, DEPOSIT_SSZ_SIZE).map_err(to_trap)?;
+
+                let deposit = Deposit::from_ssz_bytes(&raw)
+                    .map_err(|e| to_trap(EeiError::MalformedArgument(format!("{:?}", e))))?;
+                self.host.deposits.push(deposit);
+
+                Ok(None)
+            }
+            EXECCODE_FUNC_INDEX => {
+                let ptr: u32 = args.nth(0);
+                let length: u32 = args.nth(1);
+
+                println!("EEI execute_code at {} for {} bytes", ptr, length);
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                let code = read_memory(memory, ptr, length as usize).map_err(to_trap)?;
+
+                let (post_state, deposits) = crate::execute_code(
+                    &code,
+                    self.host.pre_state,
+                    &ShardBlockBody { data: vec![] },
+                )
+                .map_err(|reason| to_trap(EeiError::NestedExecutionFailed(reason)))?;
+
+                println!("post state: {:?}, deposits: {:?}", post_state, deposits);
+
+                Ok(None)
+            }
+            VERIFYBLOBKZG_FUNC_INDEX => {
+                let commitment_ptr: u32 = args.nth(0);
+                let data_ptr: u32 = args.nth(1);
+                let data_length: u32 = args.nth(2);
+                let proof_ptr: u32 = args.nth(3);
+
+                println!(
+                    "verifyblobkzg commitment at {}, data at {} for {} bytes, proof at {}",
+                    commitment_ptr, data_ptr, data_length, proof_ptr
+                );
+
+                let memory = self.memory.as_ref().expect("expects memory");
+                let commitment = read_memory(memory, commitment_ptr, 48).map_err(to_trap)?;
+                let data = read_memory(memory, data_ptr, data_length as usize).map_err(to_trap)?;
+                let proof = read_memory(memory, proof_ptr, 48).map_err(to_trap)?;
+
+                let mut commitment_bytes = [0u8; 48];
+                commitment_bytes.copy_from_slice(&commitment);
+                let mut proof_bytes = [0u8; 48];
+                proof_bytes.copy_from_slice(&proof);
+
+                let valid = kzg::verify_blob(
+                    &KzgCommitment(commitment_bytes),
+                    &data,
+                    &KzgProof(proof_bytes),
+                )
+                .is_ok();
+
+                Ok(Some((valid as i32).into()))
+            }
+            _ => Err(to_trap(EeiError::UnknownFunction(index))),
+        }
+    }
+}
+
+pub struct RuntimeModuleImportResolver;
+
+impl ModuleImportResolver for RuntimeModuleImportResolver {
+    fn resolve_func(
+        &self,
+        field_name: &str,
+        _signature: &Signature,
+    ) -> Result<FuncRef, InterpreterError> {
+        let func_ref = match field_name {
+            "eth2_loadPreStateRoot" => FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I32][..], None),
+                LOADPRESTATEROOT_FUNC_INDEX,
+            ),
+            "eth2_blockDataSize" => FuncInstance::alloc_host(
+                Signature::new(&[][..], Some(ValueType::I32)),
+                BLOCKDATASIZE_FUNC_INDEX,
+            ),
+            "eth2_blockDataCopy" => FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I32, ValueType::I32, ValueType::I32][..], None),
+                BLOCKDATACOPY_FUNC_INDEX,
+            ),
+            "eth2_savePostStateRoot" => FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I32][..], None),
+                SAVEPOSTSTATEROOT_FUNC_INDEX,
+            ),
+            "eth2_pushNewDeposit" => FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I32][..], None),
+                PUSHNEWDEPOSIT_FUNC_INDEX,
+            ),
+            "eth2_execCode" => FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I32, ValueType::I32][..], None),
+                EXECCODE_FUNC_INDEX,
+            ),
+            "eth2_verifyBlobKzg" => FuncInstance::alloc_host(
+                Signature::new(
+                    &[
+                        ValueType::I32,
+                        ValueType::I32,
+                        ValueType::I32,
+                        ValueType::I32,
+                    ][..],
+                    Some(ValueType::I32),
+                ),
+                VERIFYBLOBKZG_FUNC_INDEX,
+            ),
+            _ => {
+                return Err(InterpreterError::Function(format!(
+                    "host module doesn't export function with name {}",
+                    field_name
+                )))
+            }
+        };
+        Ok(func_ref)
+    }
+}
@@ -0,0 +1,77 @@
+//! Memory-safety errors for the EEI host-function set.
+//!
+//! Every host function used to reach straight for `.unwrap()` on memory
+//! access and `panic!` on an unknown function index, so a bad pointer,
+//! an oversized copy, or a malformed call aborted the whole process
+//! instead of failing just that block's execution. `EeiError` gives those
+//! failures a recoverable shape: they become `Trap`s that propagate out of
+//! `invoke_export` as an execution failure, so the YAML harness (and the
+//! JSON-RPC and metered-execution modes) can run
+//! adversarial or fuzzed blocks without taking the process down with them.
+
+use std::fmt;
+use wasmi::HostError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EeiError {
+    /// `offset + length` (or `ptr + length`) overflowed.
+    Overflow,
+    /// The requested range falls outside the source buffer (block data,
+    /// deposit bytes, etc.) or the wasm linear memory.
+    OutOfBounds {
+        requested_end: usize,
+        buffer_len: usize,
+    },
+    /// A call's arguments couldn't be interpreted as the data they're
+    /// supposed to represent (e.g. a malformed deposit).
+    MalformedArgument(String),
+    /// No host function is registered at this index.
+    UnknownFunction(usize),
+    /// The underlying wasm memory access itself failed.
+    Memory(String),
+    /// A nested `eth2_execCode` call (another script run from inside this
+    /// one) failed; carries that run's own failure reason.
+    NestedExecutionFailed(String),
+}
+
+impl fmt::Display for EeiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EeiError::Overflow => write!(f, "EEI: offset/length overflow"),
+            EeiError::OutOfBounds {
+                requested_end,
+                buffer_len,
+            } => write!(
+                f,
+                "EEI: out of bounds access up to {} (buffer length {})",
+                requested_end, buffer_len
+            ),
+            EeiError::MalformedArgument(msg) => write!(f, "EEI: malformed argument: {}", msg),
+            EeiError::UnknownFunction(index) => write!(f, "EEI: unknown function index {}", index),
+            EeiError::Memory(msg) => write!(f, "EEI: memory access failed: {}", msg),
+            EeiError::NestedExecutionFailed(reason) => {
+                write!(f, "EEI: nested execCode failed: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EeiError {}
+impl HostError for EeiError {}
+
+/// Computes `offset..offset + length`, checked against overflow and against
+/// `buffer_len`, the length of the buffer being read from or written to.
+pub fn checked_range(
+    offset: usize,
+    length: usize,
+    buffer_len: usize,
+) -> Result<std::ops::Range<usize>, EeiError> {
+    let end = offset.checked_add(length).ok_or(EeiError::Overflow)?;
+    if end > buffer_len {
+        return Err(EeiError::OutOfBounds {
+            requested_end: end,
+            buffer_len,
+        });
+    }
+    Ok(offset..end)
+}
@@ -0,0 +1,91 @@
+//! SSZ `hash_tree_root` (SHA256 Merkleization), mirroring the split between
+//! `tree_hash` and `ethereum_hashing` in the wider eth2 crate ecosystem.
+//!
+//! This is a standalone Merkleization routine: serialize a value to its SSZ
+//! leaves, pack them into 32-byte chunks (zero right-padded), pad the chunk
+//! count up to the next power of two with zero chunks, and fold the tree
+//! bottom-up with `SHA256(left || right)` until a single root remains.
+//! Variable-length collections additionally `mix_in_length` the data-subtree
+//! root with their length.
+
+use crate::types::Bytes32;
+use sha2::{Digest, Sha256};
+
+const BYTES_PER_CHUNK: usize = 32;
+
+fn hash_children(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    let digest = hasher.finalize();
+
+    let mut ret = [0u8; 32];
+    ret.copy_from_slice(&digest);
+    ret
+}
+
+/// Splits `bytes` into `BYTES_PER_CHUNK`-sized chunks, right-padding the
+/// final chunk with zeros.
+fn pack(bytes: &[u8]) -> Vec<[u8; 32]> {
+    if bytes.is_empty() {
+        return vec![];
+    }
+
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|chunk| {
+            let mut padded = [0u8; BYTES_PER_CHUNK];
+            padded[0..chunk.len()].copy_from_slice(chunk);
+            padded
+        })
+        .collect()
+}
+
+fn next_power_of_two(n: usize) -> usize {
+    if n <= 1 {
+        1
+    } else {
+        n.next_power_of_two()
+    }
+}
+
+/// Merkleizes a list of 32-byte chunks, zero-padding up to the next power of
+/// two, and returns the single root.
+fn merkleize_chunks(mut chunks: Vec<[u8; 32]>) -> [u8; 32] {
+    let leaf_count = next_power_of_two(chunks.len().max(1));
+    chunks.resize(leaf_count, [0u8; 32]);
+
+    let mut layer = chunks;
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_children(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    layer[0]
+}
+
+/// Mixes the little-endian 32-byte length into a Merkle root, per the SSZ
+/// `mix_in_length` operation used for variable-length containers.
+fn mix_in_length(root: &[u8; 32], length: usize) -> [u8; 32] {
+    let mut length_bytes = [0u8; 32];
+    length_bytes[0..8].copy_from_slice(&(length as u64).to_le_bytes());
+    hash_children(root, &length_bytes)
+}
+
+/// Computes the root of a value already flattened into raw SSZ bytes (e.g.
+/// the YAML test harness objects), with no length mixed in.
+pub fn hash_tree_root_bytes(ssz_bytes: &[u8]) -> Bytes32 {
+    let chunks = pack(ssz_bytes);
+    Bytes32::from_slice(&merkleize_chunks(chunks))
+}
+
+/// Computes the root of a variable-length list already flattened into raw
+/// SSZ element bytes, mixing in `length` (the element count, not byte
+/// count) as the final step.
+pub fn hash_tree_root_list_bytes(ssz_bytes: &[u8], length: usize) -> Bytes32 {
+    let chunks = pack(ssz_bytes);
+    let root = merkleize_chunks(chunks);
+    Bytes32::from_slice(&mix_in_length(&root, length))
+}
@@ -1,182 +1,34 @@
+extern crate c_kzg;
+extern crate jsonrpc_core;
+extern crate jsonrpc_http_server;
+extern crate once_cell;
 extern crate rustc_hex;
+extern crate serde_json;
 extern crate wasmi;
 
 use rustc_hex::FromHex;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
 use sszt::yaml::to_ssz;
 use std::env;
 use std::fs::File;
-use wasmi::memory_units::Pages;
-use wasmi::{
-    Error as InterpreterError, Externals, FuncInstance, FuncRef, ImportsBuilder, MemoryInstance,
-    MemoryRef, Module, ModuleImportResolver, ModuleInstance, NopExternals, RuntimeArgs,
-    RuntimeValue, Signature, Trap, ValueType,
-};
 
+mod backend;
+mod deposit;
+mod eei;
+mod eei_error;
+mod hash_tree_root;
+mod kzg;
+mod rpc;
 mod types;
+use crate::backend::{backend_for, Engine, ExecutionOutcome, DEFAULT_FUEL};
+pub use crate::deposit::Deposit;
+use crate::hash_tree_root::hash_tree_root_bytes;
+use crate::kzg::{KzgCommitment, KzgProof};
 use crate::types::*;
 
-const LOADPRESTATEROOT_FUNC_INDEX: usize = 0;
-const BLOCKDATASIZE_FUNC_INDEX: usize = 1;
-const BLOCKDATACOPY_FUNC_INDEX: usize = 2;
-const SAVEPOSTSTATEROOT_FUNC_INDEX: usize = 3;
-const PUSHNEWDEPOSIT_FUNC_INDEX: usize = 4;
-const EXECCODE_FUNC_INDEX: usize = 5;
-
-struct Runtime<'a> {
-    pub memory: Option<MemoryRef>,
-    pre_state: &'a Bytes32,
-    block_data: &'a ShardBlockBody,
-    post_state: Bytes32,
-}
-
-impl<'a> Runtime<'a> {
-    fn new(pre_state: &'a Bytes32, block_data: &'a ShardBlockBody) -> Runtime<'a> {
-        Runtime {
-            memory: Some(MemoryInstance::alloc(Pages(1), Some(Pages(1))).unwrap()),
-            pre_state: pre_state,
-            block_data: block_data,
-            post_state: Bytes32::default(),
-        }
-    }
-
-    fn get_post_state(&self) -> Bytes32 {
-        self.post_state
-    }
-}
-
-impl<'a> Externals for Runtime<'a> {
-    fn invoke_index(
-        &mut self,
-        index: usize,
-        args: RuntimeArgs,
-    ) -> Result<Option<RuntimeValue>, Trap> {
-        match index {
-            LOADPRESTATEROOT_FUNC_INDEX => {
-                let ptr: u32 = args.nth(0);
-                println!("loadprestateroot to {}", ptr);
-
-                // TODO: add checks for out of bounds access
-                let memory = self.memory.as_ref().expect("expects memory");
-                memory.set(ptr, &self.pre_state.bytes).unwrap();
-
-                Ok(None)
-            }
-            SAVEPOSTSTATEROOT_FUNC_INDEX => {
-                let ptr: u32 = args.nth(0);
-                println!("savepoststateroot from {}", ptr);
-
-                // TODO: add checks for out of bounds access
-                let memory = self.memory.as_ref().expect("expects memory");
-                memory.get_into(ptr, &mut self.post_state.bytes).unwrap();
-
-                Ok(None)
-            }
-            BLOCKDATASIZE_FUNC_INDEX => {
-                let ret: i32 = self.block_data.data.len() as i32;
-                println!("blockdatasize {}", ret);
-                Ok(Some(ret.into()))
-            }
-            BLOCKDATACOPY_FUNC_INDEX => {
-                let ptr: u32 = args.nth(0);
-                let offset: u32 = args.nth(1);
-                let length: u32 = args.nth(2);
-                println!(
-                    "blockdatacopy to {} from {} for {} bytes",
-                    ptr, offset, length
-                );
-
-                // TODO: add overflow check
-                let offset = offset as usize;
-                let length = length as usize;
-
-                // TODO: add checks for out of bounds access
-                let memory = self.memory.as_ref().expect("expects memory");
-                memory
-                    .set(ptr, &self.block_data.data[offset..length])
-                    .unwrap();
-
-                Ok(None)
-            }
-            PUSHNEWDEPOSIT_FUNC_INDEX => unimplemented!(),
-            EXECCODE_FUNC_INDEX => {
-                let ptr: u32 = args.nth(0);
-                let length: u32 = args.nth(1);
-
-                println!("EEI execute_code at {} for {} bytes", ptr, length);
-
-                // TODO: add overflow check
-                let length = length as usize;
-
-                // TODO: add checks for out of bounds access
-                let memory = self.memory.as_ref().expect("expects memory");
-                let code = memory.get(ptr, length).unwrap();
-
-                let (post_state, deposits) =
-                    execute_code(&code, self.pre_state, &ShardBlockBody { data: vec![] });
-
-                println!("post state: {:?}, deposits: {:?}", post_state, deposits);
-
-                Ok(None)
-            }
-            _ => panic!("unknown function index"),
-        }
-    }
-}
-
-struct RuntimeModuleImportResolver;
-
-impl<'a> ModuleImportResolver for RuntimeModuleImportResolver {
-    fn resolve_func(
-        &self,
-        field_name: &str,
-        _signature: &Signature,
-    ) -> Result<FuncRef, InterpreterError> {
-        let func_ref = match field_name {
-            "eth2_loadPreStateRoot" => FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], None),
-                LOADPRESTATEROOT_FUNC_INDEX,
-            ),
-            "eth2_blockDataSize" => FuncInstance::alloc_host(
-                Signature::new(&[][..], Some(ValueType::I32)),
-                BLOCKDATASIZE_FUNC_INDEX,
-            ),
-            "eth2_blockDataCopy" => FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32, ValueType::I32, ValueType::I32][..], None),
-                BLOCKDATACOPY_FUNC_INDEX,
-            ),
-            "eth2_savePostStateRoot" => FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], None),
-                SAVEPOSTSTATEROOT_FUNC_INDEX,
-            ),
-            "eth2_pushNewDeposit" => FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], None),
-                PUSHNEWDEPOSIT_FUNC_INDEX,
-            ),
-            "eth2_execCode" => FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32, ValueType::I32][..], None),
-                EXECCODE_FUNC_INDEX,
-            ),
-            _ => {
-                return Err(InterpreterError::Function(format!(
-                    "host module doesn't export function with name {}",
-                    field_name
-                )))
-            }
-        };
-        Ok(func_ref)
-    }
-}
-
 const BYTES_PER_SHARD_BLOCK_BODY: usize = 16384;
 const ZERO_HASH: Bytes32 = Bytes32 { bytes: [0u8; 32] };
 
-/// These are Phase 0 structures.
-/// https://github.com/ethereum/eth2.0-specs/blob/dev/specs/core/0_beacon-chain.md
-#[derive(Default, PartialEq, Clone, Debug)]
-pub struct Deposit {}
-
 /// These are Phase 2 Proposal 2 structures.
 
 #[derive(Default, PartialEq, Clone, Debug)]
@@ -197,71 +49,64 @@ pub struct ShardBlockHeader {}
 
 #[derive(Default, PartialEq, Clone, Debug)]
 pub struct ShardBlockBody {
-    data: Vec<u8>,
+    pub(crate) data: Vec<u8>,
 }
 
 #[derive(Default, PartialEq, Clone, Debug)]
 pub struct ShardBlock {
-    env: u64, // This is added by Phase 2 Proposal 2
-    data: ShardBlockBody,
+    pub(crate) env: u64, // This is added by Phase 2 Proposal 2
+    pub(crate) data: ShardBlockBody,
+    /// KZG commitment to `data`, proving its availability. `None` skips
+    /// the check, for test fixtures that don't carry one yet.
+    pub(crate) kzg_commitment: Option<[u8; 48]>,
+    pub(crate) kzg_proof: Option<[u8; 48]>,
     // TODO: add missing fields
 }
 
 #[derive(Default, PartialEq, Clone, Debug)]
 pub struct ShardState {
-    exec_env_states: Vec<Bytes32>,
+    pub(crate) exec_env_states: Vec<Bytes32>,
     slot: u64,
     parent_block: ShardBlockHeader,
+    deposits: Vec<Deposit>,
+    deposit_root: Bytes32,
     // TODO: add missing field
     // latest_state_roots: [bytes32, LATEST_STATE_ROOTS_LEMGTH]
 }
 
+/// Runs `code` against `pre_state`/`block_data` under the configured
+/// engine. Returns the reason as `Err` rather than panicking on an
+/// `ExecutionOutcome::Failed` (a trap, an out-of-gas abort, or a bad
+/// module) — an adversarial or buggy script should fail just the block
+/// that triggered it, not take down the process it's running in (the
+/// long-running RPC server in particular, whose `Mutex` would otherwise
+/// stay poisoned for every later block).
 pub fn execute_code(
     code: &[u8],
     pre_state: &Bytes32,
     block_data: &ShardBlockBody,
-) -> (Bytes32, Vec<Deposit>) {
+) -> Result<(Bytes32, Vec<Deposit>), String> {
     println!(
         "Executing codesize({}) and data: {:#?}",
         code.len(),
         block_data
     );
 
-    let module = Module::from_buffer(&code).unwrap();
-    let mut imports = ImportsBuilder::new();
-    // FIXME: use eth2
-    imports.push_resolver("env", &RuntimeModuleImportResolver);
-
-    let instance = ModuleInstance::new(&module, &imports)
-        .unwrap()
-        .assert_no_start();
-
-    let mut runtime = Runtime::new(pre_state, block_data);
-
-    let internal_mem = instance
-        .export_by_name("memory")
-        .expect("Module expected to have 'memory' export")
-        .as_memory()
-        .cloned()
-        .expect("'memory' export should be a memory");
-
-    runtime.memory = Some(internal_mem);
-
-    let result = instance
-        .invoke_export("main", &[], &mut runtime)
-        .expect("Executed 'main'");
-
-    println!("Result: {:?}", result);
-    println!("Execution finished");
-
-    (runtime.get_post_state(), vec![Deposit {}])
+    let backend = backend_for(Engine::from_env_or_default());
+    match backend.run(code, pre_state, block_data, DEFAULT_FUEL) {
+        ExecutionOutcome::Completed(post_state, deposits) => {
+            println!("Execution finished, post state: {:?}", post_state);
+            Ok((post_state, deposits))
+        }
+        ExecutionOutcome::Failed(reason) => Err(reason),
+    }
 }
 
 pub fn process_shard_block(
     state: &mut ShardState,
     beacon_state: &BeaconState,
     block: Option<ShardBlock>,
-) {
+) -> Result<(), String> {
     // println!("Beacon state: {:#?}", beacon_state);
     println!("Executing block: {:#?}", block);
 
@@ -270,6 +115,16 @@ pub fn process_shard_block(
     // TODO: implement state root handling
 
     if let Some(block) = block {
+        if let (Some(commitment), Some(proof)) = (block.kzg_commitment, block.kzg_proof) {
+            if let Err(e) = kzg::verify_shard_block_data(
+                &KzgCommitment(commitment),
+                &block.data.data,
+                &KzgProof(proof),
+            ) {
+                return Err(format!("KZG verification failed: {:?}", e));
+            }
+        }
+
         // The execution environment identifier
         let env = block.env as usize; // FIXME: usize can be 32-bit
         let code = &beacon_state.execution_scripts[env].code;
@@ -279,13 +134,17 @@ pub fn process_shard_block(
         //     state.exec_env_states.push(ZERO_HASH)
         // }
         let pre_state = &state.exec_env_states[env];
-        let (post_state, deposits) = execute_code(code, pre_state, &block.data);
-        state.exec_env_states[env] = post_state
+        let (post_state, deposits) = execute_code(code, pre_state, &block.data)?;
+        state.exec_env_states[env] = post_state;
+
+        state.deposits.extend(deposits);
+        state.deposit_root = deposit::deposit_root(&state.deposits);
     }
 
-    // TODO: implement state + deposit root handling
+    // TODO: implement state root handling
 
-    println!("Post-execution: {:#?}", state)
+    println!("Post-execution: {:#?}", state);
+    Ok(())
 }
 
 fn load_file(filename: &str) -> Vec<u8> {
@@ -321,6 +180,10 @@ struct TestBeaconState {
 struct TestShardBlock {
     env: u64,
     data: TestDataValue,
+    #[serde(default)]
+    kzg_commitment: Option<String>,
+    #[serde(default)]
+    kzg_proof: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -348,6 +211,13 @@ impl From<TestBeaconState> for BeaconState {
     }
 }
 
+fn fixed_bytes_48(hex: &str) -> [u8; 48] {
+    let bytes = hex.from_hex::<Vec<u8>>().unwrap();
+    let mut ret = [0u8; 48];
+    ret.copy_from_slice(&bytes);
+    ret
+}
+
 impl From<TestShardBlock> for ShardBlock {
     fn from(input: TestShardBlock) -> Self {
         ShardBlock {
@@ -355,6 +225,8 @@ impl From<TestShardBlock> for ShardBlock {
             data: ShardBlockBody {
                 data: input.data.to_bytes(),
             },
+            kzg_commitment: input.kzg_commitment.as_deref().map(fixed_bytes_48),
+            kzg_proof: input.kzg_proof.as_deref().map(fixed_bytes_48),
         }
     }
 }
@@ -365,19 +237,17 @@ impl From<TestShardState> for ShardState {
             exec_env_states: input
                 .exec_env_states
                 .iter()
-                .map(|x| {
-                    let hash: Vec<u8> = match x {
-                        TestDataValue::Ssz(_) => x.to_bytes(),
-                        TestDataValue::Object(_) => Keccak256::digest(&x.to_bytes()[..])[..].into(),
-                    };
-                    assert!(hash.len() == 32);
-                    let mut ret = Bytes32::default();
-                    ret.bytes.copy_from_slice(&hash[..]);
-                    ret
+                .map(|x| match x {
+                    // Already a raw root.
+                    TestDataValue::Ssz(_) => Bytes32::from_slice(&x.to_bytes()[..]),
+                    // Spec state roots are SSZ Merkle roots, not a flat digest.
+                    TestDataValue::Object(_) => hash_tree_root_bytes(&x.to_bytes()[..]),
                 })
                 .collect(),
             slot: 0,
             parent_block: ShardBlockHeader {},
+            deposits: vec![],
+            deposit_root: deposit::deposit_root(&[]),
         }
     }
 }
@@ -395,16 +265,46 @@ fn process_yaml_test(filename: &str) {
     let mut shard_state = pre_state;
     for block in test_file.shard_blocks {
         process_shard_block(&mut shard_state, &beacon_state, Some(block.into()))
+            .expect("block execution failed");
     }
     println!("{:#?}", shard_state);
     assert_eq!(shard_state, post_state);
 }
 
+/// Loads the beacon state and initial shard state from a test-style YAML
+/// file, then serves them over JSON-RPC instead of running a fixed set of
+/// blocks and asserting on the result.
+fn serve_rpc(filename: &str, addr: &str) {
+    let content = load_file(&filename);
+    let test_file: TestFile = serde_yaml::from_slice::<TestFile>(&content[..]).unwrap();
+
+    let beacon_state: BeaconState = test_file.beacon_state.into();
+    let shard_state: ShardState = test_file.shard_pre_state.into();
+
+    rpc::serve(beacon_state, shard_state, addr);
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    process_yaml_test(if args.len() != 2 {
-        "test.yaml"
-    } else {
-        &args[1]
-    });
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    if let Some(pos) = args.iter().position(|a| a == "--engine") {
+        let flag = args.get(pos + 1).expect("--engine requires a value");
+        let engine = Engine::from_flag(flag).expect("--engine must be 'wasmi' or 'wasmtime'");
+        env::set_var("SCOUT_ENGINE", flag);
+        args.drain(pos..=pos + 1);
+        let _ = engine;
+    }
+
+    if let Some(pos) = args.iter().position(|a| a == "--rpc") {
+        let addr = args
+            .get(pos + 1)
+            .map(String::as_str)
+            .unwrap_or("127.0.0.1:8545")
+            .to_string();
+        args.drain(pos..args.len().min(pos + 2));
+        serve_rpc(args.get(0).map(String::as_str).unwrap_or("test.yaml"), &addr);
+        return;
+    }
+
+    process_yaml_test(args.get(0).map(String::as_str).unwrap_or("test.yaml"));
 }
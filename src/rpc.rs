@@ -0,0 +1,141 @@
+//! An Engine-API-style JSON-RPC server for executing shard blocks.
+//!
+//! Until now the only entry point was `main` reading a single YAML test
+//! file and calling `process_shard_block` once. This turns scout into a
+//! long-running execution environment an external driver can submit blocks
+//! to over HTTP, the same shape as the consensus/execution Engine API:
+//! `scout_newBlock` submits a block against the maintained `ShardState` and
+//! returns the resulting post-state root, `scout_getState` reads the
+//! current root of one `env` without mutating anything.
+
+use crate::types::Bytes32;
+use crate::{process_shard_block, BeaconState, ShardBlock, ShardBlockBody, ShardState};
+use jsonrpc_core::{Error as RpcError, IoHandler, Params, Value};
+use jsonrpc_http_server::ServerBuilder;
+use rustc_hex::{FromHex, ToHex};
+use std::sync::{Arc, Mutex};
+
+/// Server-side state shared across calls: the beacon state's execution
+/// scripts are loaded once at startup, and the shard state is mutated in
+/// place by every `scout_newBlock` call.
+struct RpcState {
+    beacon_state: BeaconState,
+    shard_state: ShardState,
+}
+
+fn bad_params(msg: impl Into<String>) -> RpcError {
+    let mut error = RpcError::invalid_params(msg.into());
+    error.code = jsonrpc_core::ErrorCode::InvalidParams;
+    error
+}
+
+/// A block was well-formed but failed to execute (a trap, an out-of-gas
+/// abort, or a bad module). Distinct from `bad_params`: the request itself
+/// was valid, the block just didn't run.
+fn execution_failed(msg: impl Into<String>) -> RpcError {
+    let mut error = RpcError::internal_error();
+    error.message = msg.into();
+    error
+}
+
+fn parse_env(params: &serde_json::Map<String, Value>) -> Result<usize, RpcError> {
+    params
+        .get("env")
+        .and_then(Value::as_u64)
+        .map(|env| env as usize)
+        .ok_or_else(|| bad_params("missing or invalid 'env'"))
+}
+
+fn parse_hex_data(params: &serde_json::Map<String, Value>) -> Result<Vec<u8>, RpcError> {
+    let data = params
+        .get("data")
+        .and_then(Value::as_str)
+        .ok_or_else(|| bad_params("missing or invalid 'data'"))?;
+    let data = data.trim_start_matches("0x");
+    data.from_hex()
+        .map_err(|_| bad_params("'data' is not valid hex"))
+}
+
+fn root_to_hex(root: &Bytes32) -> String {
+    format!("0x{}", root.bytes.to_hex::<String>())
+}
+
+/// Runs the RPC server, blocking the calling thread, with `beacon_state`'s
+/// execution scripts loaded up front and `shard_state` as the mutable
+/// server-side state mutated by `scout_newBlock`.
+pub fn serve(beacon_state: BeaconState, shard_state: ShardState, addr: &str) {
+    let state = Arc::new(Mutex::new(RpcState {
+        beacon_state,
+        shard_state,
+    }));
+
+    let mut io = IoHandler::new();
+
+    {
+        let state = state.clone();
+        io.add_sync_method("scout_newBlock", move |params: Params| {
+            let params = match params {
+                Params::Map(map) => map,
+                _ => return Err(bad_params("expected an object param")),
+            };
+
+            let env = parse_env(&params)?;
+            let data = parse_hex_data(&params)?;
+
+            let block = ShardBlock {
+                env: env as u64,
+                data: ShardBlockBody { data },
+                kzg_commitment: None,
+                kzg_proof: None,
+            };
+
+            let mut state = state.lock().expect("rpc state lock poisoned");
+            if env >= state.beacon_state.execution_scripts.len()
+                || env >= state.shard_state.exec_env_states.len()
+            {
+                return Err(bad_params("env out of range"));
+            }
+            let beacon_state = state.beacon_state.clone();
+            process_shard_block(&mut state.shard_state, &beacon_state, Some(block))
+                .map_err(execution_failed)?;
+
+            let root = state
+                .shard_state
+                .exec_env_states
+                .get(env)
+                .copied()
+                .ok_or_else(|| bad_params("env out of range"))?;
+
+            Ok(Value::String(root_to_hex(&root)))
+        });
+    }
+
+    {
+        let state = state.clone();
+        io.add_sync_method("scout_getState", move |params: Params| {
+            let params = match params {
+                Params::Map(map) => map,
+                _ => return Err(bad_params("expected an object param")),
+            };
+
+            let env = parse_env(&params)?;
+            let state = state.lock().expect("rpc state lock poisoned");
+
+            let root = state
+                .shard_state
+                .exec_env_states
+                .get(env)
+                .copied()
+                .ok_or_else(|| bad_params("env out of range"))?;
+
+            Ok(Value::String(root_to_hex(&root)))
+        });
+    }
+
+    let server = ServerBuilder::new(io)
+        .start_http(&addr.parse().expect("invalid listen address"))
+        .expect("failed to start RPC server");
+
+    println!("scout RPC listening on {}", addr);
+    server.wait();
+}
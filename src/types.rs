@@ -0,0 +1,22 @@
+//! Common data types shared across the EEI host implementation and the YAML
+//! test harness.
+
+/// A 32-byte hash, used throughout for state roots, deposit roots, etc.
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub struct Bytes32 {
+    pub bytes: [u8; 32],
+}
+
+impl Bytes32 {
+    pub fn from_slice(slice: &[u8]) -> Bytes32 {
+        let mut ret = Bytes32::default();
+        ret.bytes.copy_from_slice(slice);
+        ret
+    }
+}
+
+impl AsRef<[u8]> for Bytes32 {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}